@@ -0,0 +1,207 @@
+//! C ABI
+//!
+//! Exposes the runtime-dispatched HTML escaper (AVX2/SSE2/NEON/scalar,
+//! whichever [`escape_to_buf`] picked for this machine) over a stable
+//! `extern "C"` surface, so C, C++, or Python can link just this escaper
+//! without pulling in the rest of the template engine. Modeled after the
+//! C API wrapper rust-url ships for its own string routines.
+//!
+//! Every fallible entry point returns a `SAILFISH_*` status code, except
+//! allocation failure: like the rest of Rust's `Vec`/`String`, this module
+//! aborts the process on OOM rather than reporting it through a return
+//! value.
+
+use std::os::raw::c_int;
+use std::slice;
+
+use crate::runtime::buffer::Buffer;
+
+use super::escape_to_buf;
+
+/// Growable byte buffer owned across the FFI boundary.
+///
+/// Always create one with [`sailfish_buffer_new`] and release it with
+/// [`sailfish_buffer_free`] exactly once.
+#[repr(C)]
+pub struct SailfishBuffer {
+    ptr: *mut u8,
+    len: usize,
+    cap: usize,
+}
+
+/// The call completed successfully.
+pub const SAILFISH_OK: c_int = 0;
+/// The input bytes were not valid UTF-8.
+pub const SAILFISH_ERR_INVALID_UTF8: c_int = 1;
+/// A required pointer argument was null.
+pub const SAILFISH_ERR_NULL_POINTER: c_int = 2;
+
+/// Allocate a new, empty [`SailfishBuffer`].
+#[no_mangle]
+pub extern "C" fn sailfish_buffer_new() -> SailfishBuffer {
+    buffer_into_raw(Buffer::new())
+}
+
+/// Append `len` bytes at `ptr` to `buf`. The bytes must be valid UTF-8.
+///
+/// # Safety
+///
+/// `buf` must point to a live [`SailfishBuffer`] previously returned by this
+/// module, and `ptr` must be valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sailfish_buffer_append(
+    buf: *mut SailfishBuffer,
+    ptr: *const u8,
+    len: usize,
+) -> c_int {
+    if buf.is_null() || (ptr.is_null() && len != 0) {
+        return SAILFISH_ERR_NULL_POINTER;
+    }
+
+    let bytes = slice::from_raw_parts(ptr, len);
+    let s = match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => return SAILFISH_ERR_INVALID_UTF8,
+    };
+
+    let mut buffer = buffer_from_raw(&*buf);
+    buffer.push_str(s);
+    *buf = buffer_into_raw(buffer);
+    SAILFISH_OK
+}
+
+/// Consume `buf`, handing its contents back as a raw `(ptr, len, cap)`
+/// triple.
+///
+/// The caller takes ownership of the returned allocation and must eventually
+/// free it with [`sailfish_bytes_free`], passing back all three values
+/// unchanged — `cap` is the real Rust allocation size, which is usually
+/// larger than `len`, so freeing with just `(ptr, len)` corrupts the heap.
+///
+/// # Safety
+///
+/// `out_ptr`, `out_len`, and `out_cap` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sailfish_buffer_into_bytes(
+    buf: SailfishBuffer,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+    out_cap: *mut usize,
+) {
+    *out_ptr = buf.ptr;
+    *out_len = buf.len;
+    *out_cap = buf.cap;
+}
+
+/// Free a `(ptr, len, cap)` triple previously returned by
+/// [`sailfish_buffer_into_bytes`].
+///
+/// # Safety
+///
+/// `ptr`, `len`, and `cap` must be exactly the values
+/// [`sailfish_buffer_into_bytes`] wrote out, and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn sailfish_bytes_free(ptr: *mut u8, len: usize, cap: usize) {
+    if ptr.is_null() {
+        return;
+    }
+
+    drop(Vec::from_raw_parts(ptr, len, cap));
+}
+
+/// Free a [`SailfishBuffer`] previously returned by this module.
+///
+/// # Safety
+///
+/// `buf` must not be used again after this call, and must not have already
+/// been consumed by [`sailfish_buffer_into_bytes`].
+#[no_mangle]
+pub unsafe extern "C" fn sailfish_buffer_free(buf: SailfishBuffer) {
+    if buf.ptr.is_null() {
+        return;
+    }
+
+    drop(Vec::from_raw_parts(buf.ptr, buf.len, buf.cap));
+}
+
+/// HTML-escape the `len` bytes at `ptr` (which must be valid UTF-8),
+/// appending the result to `*out`.
+///
+/// # Safety
+///
+/// `ptr` must be valid for `len` bytes, and `out` must point to a live
+/// [`SailfishBuffer`] previously returned by this module.
+#[no_mangle]
+pub unsafe extern "C" fn sailfish_escape(
+    ptr: *const u8,
+    len: usize,
+    out: *mut SailfishBuffer,
+) -> c_int {
+    if out.is_null() || (ptr.is_null() && len != 0) {
+        return SAILFISH_ERR_NULL_POINTER;
+    }
+
+    let bytes = slice::from_raw_parts(ptr, len);
+    let feed = match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => return SAILFISH_ERR_INVALID_UTF8,
+    };
+
+    let mut buffer = buffer_from_raw(&*out);
+    escape_to_buf(feed, &mut buffer);
+    *out = buffer_into_raw(buffer);
+    SAILFISH_OK
+}
+
+unsafe fn buffer_from_raw(buf: &SailfishBuffer) -> Buffer {
+    if buf.ptr.is_null() {
+        Buffer::new()
+    } else {
+        let bytes = Vec::from_raw_parts(buf.ptr, buf.len, buf.cap);
+        Buffer::from(String::from_utf8_unchecked(bytes))
+    }
+}
+
+fn buffer_into_raw(buf: Buffer) -> SailfishBuffer {
+    let mut s = buf.into_string();
+    let ptr = s.as_mut_ptr();
+    let len = s.len();
+    let cap = s.capacity();
+    std::mem::forget(s);
+    SailfishBuffer { ptr, len, cap }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_the_c_abi() {
+        let input = "<h1>Hello, \"world\"</h1>";
+        let mut out = sailfish_buffer_new();
+
+        unsafe {
+            let code = sailfish_escape(input.as_ptr(), input.len(), &mut out);
+            assert_eq!(code, SAILFISH_OK);
+
+            let mut ptr = std::ptr::null_mut();
+            let mut len = 0;
+            let mut cap = 0;
+            sailfish_buffer_into_bytes(out, &mut ptr, &mut len, &mut cap);
+            let escaped = String::from_utf8(Vec::from_raw_parts(ptr, len, cap)).unwrap();
+            assert_eq!(escaped, "&lt;h1&gt;Hello, &quot;world&quot;&lt;/h1&gt;");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_utf8() {
+        let invalid = [0xff, 0xfe];
+        let mut out = sailfish_buffer_new();
+
+        unsafe {
+            let code = sailfish_escape(invalid.as_ptr(), invalid.len(), &mut out);
+            assert_eq!(code, SAILFISH_ERR_INVALID_UTF8);
+            sailfish_buffer_free(out);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! User-defined escape sets
+//!
+//! The default [`escape`](super::escape) routine only ever replaces the five
+//! characters `&"'<>`. [`EscapeSet`] lets callers compile their own
+//! pattern/replacement table for contexts the default rules don't cover
+//! (HTML attributes, JS strings, XML, ...) without writing a SIMD routine by
+//! hand.
+
+use super::Buffer;
+use super::UnicodeGuard;
+
+const NO_MATCH: u32 = u32::MAX;
+
+/// A compiled escaper built from a set of `pattern -> replacement` rules.
+///
+/// When every pattern is a single byte, matching goes through a 256-entry
+/// lookup table, same as the built-in `&"'<>` escaper. As soon as any
+/// pattern is longer than one byte, *all* patterns (including single-byte
+/// ones) are compiled into a trie instead, so a short pattern that happens
+/// to be a prefix of a longer one (e.g. `<` and `</script>`) never wins over
+/// the longest match.
+pub struct EscapeSet {
+    byte_table: Option<[u32; 256]>,
+    trie: Option<RestartTrie>,
+    replacements: Vec<Box<str>>,
+    unicode_guard: UnicodeGuard,
+}
+
+impl EscapeSet {
+    /// Start building a new escaper.
+    pub fn builder() -> EscapeSetBuilder {
+        EscapeSetBuilder::new()
+    }
+
+    /// Write the escaped contents of `feed` into `buf`.
+    pub fn escape_to_buf(&self, feed: &str, buf: &mut Buffer) {
+        let bytes = feed.as_bytes();
+        let mut run_start = 0;
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let b = bytes[i];
+
+            if let Some(trie) = &self.trie {
+                if let Some((end, idx)) = trie.longest_match(bytes, i) {
+                    if i > run_start {
+                        buf.push_str(&feed[run_start..i]);
+                    }
+
+                    buf.push_str(&self.replacements[idx as usize]);
+                    run_start = end;
+                    i = end;
+                    continue;
+                }
+            }
+
+            if let Some(table) = &self.byte_table {
+                let idx = table[b as usize];
+
+                if idx != NO_MATCH {
+                    if i > run_start {
+                        buf.push_str(&feed[run_start..i]);
+                    }
+
+                    buf.push_str(&self.replacements[idx as usize]);
+                    run_start = i + 1;
+                    i += 1;
+                    continue;
+                }
+            }
+
+            if b >= 0x80 && self.unicode_guard != UnicodeGuard::default() {
+                let ch = feed[i..].chars().next().expect("i is a char boundary");
+
+                if self.unicode_guard.is_dangerous(ch) {
+                    if i > run_start {
+                        buf.push_str(&feed[run_start..i]);
+                    }
+
+                    buf.push_str(&format!("&#x{:04X};", ch as u32));
+                    i += ch.len_utf8();
+                    run_start = i;
+                    continue;
+                }
+
+                i += ch.len_utf8();
+                continue;
+            }
+
+            i += 1;
+        }
+
+        if run_start < bytes.len() {
+            buf.push_str(&feed[run_start..]);
+        }
+    }
+
+    /// Write the escaped contents of `feed` into `s`.
+    pub fn escape_to_string(&self, feed: &str, s: &mut String) {
+        let mut s2 = String::new();
+        std::mem::swap(s, &mut s2);
+        let mut buf = Buffer::from(s2);
+        self.escape_to_buf(feed, &mut buf);
+        let mut s2 = buf.into_string();
+        std::mem::swap(s, &mut s2);
+    }
+}
+
+impl Default for EscapeSet {
+    /// The same five characters sailfish has always escaped.
+    fn default() -> Self {
+        EscapeSet::builder()
+            .escape("\"", "&quot;")
+            .escape("&", "&amp;")
+            .escape("'", "&#039;")
+            .escape("<", "&lt;")
+            .escape(">", "&gt;")
+            .build()
+    }
+}
+
+/// Builds an [`EscapeSet`] from a list of `pattern -> replacement` rules.
+pub struct EscapeSetBuilder {
+    patterns: Vec<(Box<str>, Box<str>)>,
+    unicode_guard: UnicodeGuard,
+}
+
+impl EscapeSetBuilder {
+    fn new() -> Self {
+        Self {
+            patterns: Vec::new(),
+            unicode_guard: UnicodeGuard::default(),
+        }
+    }
+
+    /// Register a pattern (a single character or an arbitrary string) and
+    /// the replacement text emitted whenever it is found.
+    pub fn escape<P, R>(mut self, pattern: P, replacement: R) -> Self
+    where
+        P: Into<Box<str>>,
+        R: Into<Box<str>>,
+    {
+        self.patterns.push((pattern.into(), replacement.into()));
+        self
+    }
+
+    /// Opt into neutralizing dangerous Unicode formatting codepoints (bidi
+    /// overrides, line/paragraph separators, and optionally zero-width/BOM
+    /// characters) by rewriting them as `&#xNNNN;` numeric character
+    /// references. See [`UnicodeGuard`] for exactly what each flag covers.
+    pub fn unicode_guard(mut self, guard: UnicodeGuard) -> Self {
+        self.unicode_guard = guard;
+        self
+    }
+
+    /// Compile the registered rules into a reusable [`EscapeSet`].
+    pub fn build(self) -> EscapeSet {
+        let has_multi_byte = self
+            .patterns
+            .iter()
+            .any(|(pattern, _)| pattern.as_bytes().len() > 1);
+
+        let mut replacements = Vec::with_capacity(self.patterns.len());
+        let mut byte_table = [NO_MATCH; 256];
+        let mut has_byte_table = false;
+        let mut trie_patterns: Vec<(Vec<u8>, u32)> = Vec::new();
+
+        for (pattern, replacement) in self.patterns {
+            let idx = replacements.len() as u32;
+            let bytes = pattern.as_bytes();
+
+            if has_multi_byte {
+                // Every pattern goes through the trie here, even single-byte
+                // ones, so a short pattern that is a prefix of a longer one
+                // (e.g. `<` and `</script>`) never preempts the longest match.
+                trie_patterns.push((bytes.to_vec(), idx));
+            } else if bytes.len() == 1 {
+                byte_table[bytes[0] as usize] = idx;
+                has_byte_table = true;
+            }
+
+            replacements.push(replacement);
+        }
+
+        EscapeSet {
+            byte_table: if has_byte_table {
+                Some(byte_table)
+            } else {
+                None
+            },
+            trie: if trie_patterns.is_empty() {
+                None
+            } else {
+                Some(RestartTrie::build(&trie_patterns))
+            },
+            replacements,
+            unicode_guard: self.unicode_guard,
+        }
+    }
+}
+
+/// A plain goto-only trie of registered patterns, re-walked from the root at
+/// every input position.
+///
+/// This is *not* Aho-Corasick: there are no failure links, so matching a
+/// pattern is O(pattern_len) per starting byte rather than amortized O(1).
+/// That's the right trade here — [`EscapeSet`] patterns are short and few,
+/// and [`RestartTrie::longest_match`] only needs to answer "what's the
+/// longest registered pattern starting exactly here", which a restart is the
+/// simplest way to get right.
+struct RestartTrie {
+    children: Vec<[u32; 256]>,
+    output: Vec<Option<u32>>,
+}
+
+impl RestartTrie {
+    fn build(patterns: &[(Vec<u8>, u32)]) -> Self {
+        let mut children = vec![[NO_MATCH; 256]];
+        let mut output = vec![None];
+
+        for (pattern, idx) in patterns {
+            let mut node = 0usize;
+
+            for &b in pattern {
+                let next = children[node][b as usize];
+
+                node = if next == NO_MATCH {
+                    children.push([NO_MATCH; 256]);
+                    output.push(None);
+                    let new_node = (children.len() - 1) as u32;
+                    children[node][b as usize] = new_node;
+                    new_node as usize
+                } else {
+                    next as usize
+                };
+            }
+
+            output[node] = Some(*idx);
+        }
+
+        Self { children, output }
+    }
+
+    /// Walk the trie starting at `bytes[start..]`, returning the end
+    /// position and replacement index of the longest registered pattern
+    /// found there, or `None` if no registered pattern starts at `start`.
+    fn longest_match(&self, bytes: &[u8], start: usize) -> Option<(usize, u32)> {
+        let mut node = 0usize;
+        let mut best = None;
+        let mut i = start;
+
+        while i < bytes.len() {
+            let next = self.children[node][bytes[i] as usize];
+
+            if next == NO_MATCH {
+                break;
+            }
+
+            node = next as usize;
+            i += 1;
+
+            if let Some(idx) = self.output[node] {
+                best = Some((i, idx));
+            }
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escape(set: &EscapeSet, feed: &str) -> String {
+        let mut s = String::new();
+        set.escape_to_string(feed, &mut s);
+        s
+    }
+
+    #[test]
+    fn default_matches_builtin_escape() {
+        let set = EscapeSet::default();
+        assert_eq!(
+            escape(&set, "<h1>Hello, \"world\"</h1>"),
+            "&lt;h1&gt;Hello, &quot;world&quot;&lt;/h1&gt;"
+        );
+    }
+
+    #[test]
+    fn single_byte_custom_set() {
+        let set = EscapeSet::builder()
+            .escape("=", "&#061;")
+            .escape("`", "&#096;")
+            .build();
+        assert_eq!(escape(&set, "a=`b"), "a&#061;&#096;b");
+    }
+
+    #[test]
+    fn multi_byte_pattern_via_trie() {
+        let set = EscapeSet::builder()
+            .escape("</script>", "<\\/script>")
+            .build();
+        assert_eq!(escape(&set, "<script>x</script>"), "<script>x<\\/script>");
+        assert_eq!(escape(&set, "no match here"), "no match here");
+    }
+
+    #[test]
+    fn longer_pattern_wins_over_a_single_byte_prefix() {
+        let set = EscapeSet::builder()
+            .escape("<", "&lt;")
+            .escape("</script>", "<\\/script>")
+            .build();
+
+        assert_eq!(
+            escape(&set, "<script>x</script>"),
+            "&lt;script>x<\\/script>"
+        );
+        assert_eq!(escape(&set, "a<b"), "a&lt;b");
+    }
+
+    #[test]
+    fn unicode_guard_is_opt_in() {
+        let set = EscapeSet::builder()
+            .escape("<", "&lt;")
+            .unicode_guard(UnicodeGuard {
+                neutralize_bidi_and_separators: true,
+                ..UnicodeGuard::default()
+            })
+            .build();
+
+        assert_eq!(escape(&set, "a\u{202E}<b>"), "a&#x202E;&lt;b>");
+        assert_eq!(escape(&EscapeSet::default(), "a\u{202E}b"), "a\u{202E}b");
+    }
+
+    #[test]
+    fn unicode_guard_leaves_safe_multibyte_chars_alone() {
+        let set = EscapeSet::builder()
+            .unicode_guard(UnicodeGuard {
+                neutralize_bidi_and_separators: true,
+                neutralize_zero_width: true,
+            })
+            .build();
+
+        assert_eq!(escape(&set, "café 漢字 🎉"), "café 漢字 🎉");
+    }
+}
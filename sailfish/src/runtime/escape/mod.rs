@@ -3,15 +3,25 @@
 //! By default sailfish replaces the characters `&"'<>` with the equivalent html.
 
 mod avx2;
+#[cfg(feature = "capi")]
+mod capi;
+mod custom;
 mod fallback;
 mod naive;
+#[cfg(target_arch = "aarch64")]
+mod neon;
 mod sse2;
 
+#[cfg(feature = "capi")]
+pub use capi::*;
+
 use std::mem;
 use std::sync::atomic::{AtomicPtr, Ordering};
 
 use super::buffer::Buffer;
 
+pub use custom::{EscapeSet, EscapeSetBuilder};
+
 type FnRaw = *mut ();
 
 static ESCAPE_LUT: [u8; 256] = [
@@ -38,7 +48,7 @@ pub fn escape(feed: &str, buf: &mut Buffer) {
 }
 
 /// default escape function
-#[cfg(not(target_feature = "avx2"))]
+#[cfg(all(not(target_feature = "avx2"), any(target_arch = "x86", target_arch = "x86_64")))]
 pub fn escape(feed: &str, buf: &mut Buffer) {
     let fun = if is_x86_feature_detected!("avx2") {
         avx2::escape
@@ -52,6 +62,27 @@ pub fn escape(feed: &str, buf: &mut Buffer) {
     unsafe { fun(feed, buf) };
 }
 
+/// default escape function for aarch64 targets
+///
+/// NEON is baseline on aarch64 (unlike AVX2/SSE2 on x86), so there's no
+/// runtime feature to detect here; we always dispatch straight to it.
+#[cfg(target_arch = "aarch64")]
+pub fn escape(feed: &str, buf: &mut Buffer) {
+    FN.store(neon::escape as FnRaw, Ordering::Relaxed);
+    unsafe { neon::escape(feed, buf) };
+}
+
+#[cfg(not(any(
+    target_feature = "avx2",
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "aarch64"
+)))]
+pub fn escape(feed: &str, buf: &mut Buffer) {
+    FN.store(fallback::escape as FnRaw, Ordering::Relaxed);
+    unsafe { fallback::escape(feed, buf) };
+}
+
 pub fn register_escape_fn(fun: fn(&str, &mut Buffer)) {
     FN.store(fun as FnRaw, Ordering::Relaxed);
 }
@@ -64,6 +95,84 @@ pub(crate) fn escape_to_buf(feed: &str, buf: &mut Buffer) {
     }
 }
 
+/// Which additional Unicode codepoints [`escape_to_buf_guarded`] neutralizes
+/// on top of the default `&"'<>` set.
+///
+/// All fields default to `false`, so enabling a [`UnicodeGuard`] never
+/// changes behavior or performance unless explicitly requested.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UnicodeGuard {
+    /// Rewrite bidirectional overrides/embeddings/isolates (`U+202A`-`U+202E`,
+    /// `U+2066`-`U+2069`) and the line/paragraph separators `U+2028`/`U+2029`
+    /// as `&#xNNNN;` numeric character references, closing off
+    /// Trojan-Source-style spoofing of interpolated text.
+    pub neutralize_bidi_and_separators: bool,
+
+    /// Additionally rewrite zero-width and BOM codepoints
+    /// (`U+200B`-`U+200F`, `U+FEFF`) the same way.
+    pub neutralize_zero_width: bool,
+}
+
+impl UnicodeGuard {
+    fn is_dangerous(self, c: char) -> bool {
+        let cp = c as u32;
+
+        if self.neutralize_bidi_and_separators
+            && ((0x202A..=0x202E).contains(&cp)
+                || (0x2066..=0x2069).contains(&cp)
+                || cp == 0x2028
+                || cp == 0x2029)
+        {
+            return true;
+        }
+
+        if self.neutralize_zero_width && ((0x200B..=0x200F).contains(&cp) || cp == 0xFEFF) {
+            return true;
+        }
+
+        false
+    }
+}
+
+/// Like [`escape_to_buf`], but additionally neutralizes the Unicode
+/// codepoints enabled in `guard`.
+///
+/// The text between flagged codepoints is still escaped through the
+/// ordinary SIMD-dispatched [`escape_to_buf`]; only the scalar decode of
+/// non-ASCII scalars needed to test `guard` membership happens here.
+pub fn escape_to_buf_guarded(feed: &str, buf: &mut Buffer, guard: UnicodeGuard) {
+    if guard == UnicodeGuard::default() {
+        escape_to_buf(feed, buf);
+        return;
+    }
+
+    let bytes = feed.as_bytes();
+    let mut run_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] < 0x80 {
+            i += 1;
+            continue;
+        }
+
+        let c = feed[i..].chars().next().expect("i is a char boundary");
+
+        if guard.is_dangerous(c) {
+            escape_to_buf(&feed[run_start..i], buf);
+            buf.push_str(&format!("&#x{:04X};", c as u32));
+            i += c.len_utf8();
+            run_start = i;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+
+    if run_start < bytes.len() {
+        escape_to_buf(&feed[run_start..], buf);
+    }
+}
+
 /// write the escaped contents into `String`
 ///
 /// # Examples
@@ -133,6 +242,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn guarded_default_is_noop() {
+        let mut buf = Buffer::new();
+        escape_to_buf_guarded("<a>日本語</a>", &mut buf, UnicodeGuard::default());
+        assert_eq!(buf.as_str(), "&lt;a&gt;日本語&lt;/a&gt;");
+    }
+
+    #[test]
+    fn guarded_neutralizes_bidi_override() {
+        let mut buf = Buffer::new();
+        let guard = UnicodeGuard {
+            neutralize_bidi_and_separators: true,
+            ..UnicodeGuard::default()
+        };
+        escape_to_buf_guarded("a\u{202E}b<c>", &mut buf, guard);
+        assert_eq!(buf.as_str(), "a&#x202E;b&lt;c&gt;");
+    }
+
+    #[test]
+    fn guarded_neutralizes_zero_width_when_enabled() {
+        let mut buf = Buffer::new();
+        let guard = UnicodeGuard {
+            neutralize_zero_width: true,
+            ..UnicodeGuard::default()
+        };
+        escape_to_buf_guarded("a\u{200B}b", &mut buf, guard);
+        assert_eq!(buf.as_str(), "a&#x200B;b");
+
+        let mut buf2 = Buffer::new();
+        escape_to_buf_guarded("a\u{200B}b", &mut buf2, UnicodeGuard::default());
+        assert_eq!(buf2.as_str(), "a\u{200B}b");
+    }
+
     #[test]
     fn random() {
         const ASCII_CHARS: &'static [u8] = br##"abcdefghijklmnopqrstuvwxyz0123456789-^\@[;:],./\!"#$%&'()~=~|`{+*}<>?_"##;
@@ -168,6 +310,13 @@ mod tests {
 
             assert_eq!(buf1.as_str(), buf3.as_str());
             assert_eq!(buf2.as_str(), buf3.as_str());
+
+            #[cfg(target_arch = "aarch64")]
+            {
+                let mut buf4 = Buffer::new();
+                unsafe { neon::escape(s, &mut buf4) };
+                assert_eq!(buf4.as_str(), buf3.as_str());
+            }
         }
     }
 }
@@ -0,0 +1,85 @@
+//! NEON-accelerated escaping for aarch64
+//!
+//! NEON is part of the aarch64 baseline instruction set, so unlike the x86
+//! `avx2`/`sse2` backends this one needs no runtime feature detection.
+
+use std::arch::aarch64::*;
+
+use super::Buffer;
+use super::ESCAPED;
+
+const VECTOR_SIZE: usize = 16;
+
+pub(crate) unsafe fn escape(feed: &str, buf: &mut Buffer) {
+    let bytes = feed.as_bytes();
+    let mut start = 0;
+    let mut pos = 0;
+
+    while pos + VECTOR_SIZE <= bytes.len() {
+        let chunk = vld1q_u8(bytes.as_ptr().add(pos));
+        let mask = delimiter_mask(chunk);
+        let bits = move_mask(mask);
+
+        if bits == 0 {
+            pos += VECTOR_SIZE;
+            continue;
+        }
+
+        let first = (bits.trailing_zeros() / 4) as usize;
+        buf.push_str(&feed[start..pos + first]);
+        push_escaped(buf, bytes[pos + first]);
+        pos += first + 1;
+        start = pos;
+    }
+
+    while pos < bytes.len() {
+        if let Some(idx) = escape_index(bytes[pos]) {
+            buf.push_str(&feed[start..pos]);
+            buf.push_str(ESCAPED[idx]);
+            start = pos + 1;
+        }
+        pos += 1;
+    }
+
+    buf.push_str(&feed[start..]);
+}
+
+#[inline]
+unsafe fn delimiter_mask(chunk: uint8x16_t) -> uint8x16_t {
+    let quot = vceqq_u8(chunk, vdupq_n_u8(b'"'));
+    let amp = vceqq_u8(chunk, vdupq_n_u8(b'&'));
+    let apos = vceqq_u8(chunk, vdupq_n_u8(b'\''));
+    let lt = vceqq_u8(chunk, vdupq_n_u8(b'<'));
+    let gt = vceqq_u8(chunk, vdupq_n_u8(b'>'));
+
+    vorrq_u8(vorrq_u8(vorrq_u8(quot, amp), vorrq_u8(apos, lt)), gt)
+}
+
+/// Shift each 16-bit lane right by 4 and narrow it to 8 bits, so every input
+/// byte becomes one nibble of a 64-bit value (`0xF` for a matching byte, `0`
+/// otherwise). This is
+/// the standard aarch64 substitute for the x86 `_mm_movemask_epi8`.
+#[inline]
+unsafe fn move_mask(v: uint8x16_t) -> u64 {
+    let widened = vreinterpretq_u16_u8(v);
+    let narrowed = vshrn_n_u16(widened, 4);
+    vget_lane_u64(vreinterpret_u64_u8(narrowed), 0)
+}
+
+#[inline]
+fn escape_index(b: u8) -> Option<usize> {
+    match b {
+        b'"' => Some(0),
+        b'&' => Some(1),
+        b'\'' => Some(2),
+        b'<' => Some(3),
+        b'>' => Some(4),
+        _ => None,
+    }
+}
+
+#[inline]
+fn push_escaped(buf: &mut Buffer, b: u8) {
+    let idx = escape_index(b).expect("byte flagged by delimiter_mask must be one of &\"'<>");
+    buf.push_str(ESCAPED[idx]);
+}